@@ -1,11 +1,27 @@
 use axum_server::Server;
 use axum::{routing::get, Router, Json};
+use axum::extract::RawQuery;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use serde::Serialize;
 use sysinfo::{System, Disks};
 use std::net::SocketAddr;
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard};
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::fmt;
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 
+/// How often the background sampler refreshes `SYSTEM`/`DISKS` and appends a
+/// new entry to `HISTORY`.
+const SAMPLE_INTERVAL_SECS: u64 = 5;
+/// Number of samples kept in the rolling history window (5 minutes at the
+/// default interval).
+const HISTORY_CAPACITY: usize = 60;
+/// Smoothing factor for the CPU usage exponential moving average.
+const EMA_ALPHA: f32 = 0.3;
+
 #[derive(Serialize)]
 struct ServerStats {
     cpu_usage: String,
@@ -20,17 +36,231 @@ struct UsageInfo {
     percentage: String,
 }
 
+#[derive(Serialize)]
+struct UsageInfoRaw {
+    used: u64,
+    total: u64,
+    percentage: f64,
+}
+
+#[derive(Serialize)]
+struct ServerStatsRaw {
+    cpu_usage: f64,
+    ram: UsageInfoRaw,
+    storage: UsageInfoRaw,
+}
+
+/// `/stats` keeps its pretty, human-formatted strings by default but can
+/// emit `ServerStatsRaw`'s plain numbers instead via `?format=raw`, so both
+/// representations are derived from the same numeric values rather than
+/// reparsed from one another.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ServerStatsResponse {
+    Pretty(ServerStats),
+    Raw(ServerStatsRaw),
+}
+
+struct StatsQuery {
+    format: Option<String>,
+    unit: Option<String>,
+}
+
+impl StatsQuery {
+    /// Parsed by hand instead of via axum's `Query` extractor so a malformed
+    /// query string can't short-circuit the response with axum's default
+    /// plain-text rejection — every other failure in this API responds with
+    /// the same `StatError` JSON body, and an unparsed/unknown param here
+    /// just falls back to the default (pretty, auto-scaled) behavior.
+    fn from_raw(raw: Option<&str>) -> StatsQuery {
+        let mut format = None;
+        let mut unit = None;
+
+        for pair in raw.unwrap_or_default().split('&') {
+            let mut parts = pair.splitn(2, '=');
+            let key = parts.next().unwrap_or_default();
+            let value = parts.next().unwrap_or_default().to_string();
+            match key {
+                "format" => format = Some(value),
+                "unit" => unit = Some(value),
+                _ => {}
+            }
+        }
+
+        StatsQuery { format, unit }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ByteUnit {
+    B,
+    Kb,
+    Mb,
+    Gb,
+    Tb,
+    Auto,
+}
+
+impl ByteUnit {
+    fn from_query(value: Option<&str>) -> ByteUnit {
+        match value.map(str::to_ascii_lowercase).as_deref() {
+            Some("b") => ByteUnit::B,
+            Some("kb") => ByteUnit::Kb,
+            Some("mb") => ByteUnit::Mb,
+            Some("gb") => ByteUnit::Gb,
+            Some("tb") => ByteUnit::Tb,
+            _ => ByteUnit::Auto,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CpuTimeBreakdown {
+    user: String,
+    system: String,
+    idle: String,
+    nice: String,
+}
+
+#[derive(Serialize)]
+struct CpuStats {
+    cores: Vec<String>,
+    breakdown: CpuTimeBreakdown,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct CpuTimes {
+    user: u64,
+    system: u64,
+    idle: u64,
+    nice: u64,
+}
+
+#[derive(Serialize, Clone)]
+struct StatSample {
+    timestamp: DateTime<Utc>,
+    cpu_usage: f32,
+    cpu_usage_ema: f32,
+    ram_percentage: f32,
+    storage_percentage: f32,
+}
+
+#[derive(Serialize)]
+struct MinMaxAvg {
+    min: f32,
+    max: f32,
+    avg: f32,
+}
+
+#[derive(Serialize)]
+struct HistoryStats {
+    cpu: MinMaxAvg,
+    ram: MinMaxAvg,
+    storage: MinMaxAvg,
+}
+
+#[derive(Serialize)]
+struct HistoryResponse {
+    samples: Vec<StatSample>,
+    stats: HistoryStats,
+}
+
+#[derive(Serialize)]
+struct DiskInfo {
+    mount_point: String,
+    file_system: String,
+    device_name: String,
+    total: String,
+    available: String,
+    used: String,
+    percentage: String,
+    is_removable: bool,
+    is_read_only: bool,
+}
+
+#[derive(Serialize)]
+struct LoadAverage {
+    one: f64,
+    five: f64,
+    fifteen: f64,
+}
+
+#[derive(Serialize)]
+struct SystemInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    load_average: Option<LoadAverage>,
+    uptime_seconds: u64,
+    boot_time: u64,
+    hostname: Option<String>,
+    os_name: Option<String>,
+    os_version: Option<String>,
+    kernel_version: Option<String>,
+    process_count: usize,
+}
+
+/// Errors a stats handler can fail with, mapped to a clean 500 response
+/// instead of panicking the request task.
+#[derive(Debug)]
+enum StatError {
+    Cpu,
+    Memory,
+    Disk,
+    LockPoisoned,
+}
+
+impl fmt::Display for StatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatError::Cpu => write!(f, "no CPU core data available"),
+            StatError::Memory => write!(f, "total memory is reported as zero"),
+            StatError::Disk => write!(f, "total disk space is reported as zero"),
+            StatError::LockPoisoned => write!(f, "internal stats lock was poisoned"),
+        }
+    }
+}
+
+impl std::error::Error for StatError {}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for StatError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ErrorBody { error: self.to_string() })).into_response()
+    }
+}
+
+fn lock_system() -> Result<MutexGuard<'static, System>, StatError> {
+    SYSTEM.lock().map_err(|_| StatError::LockPoisoned)
+}
+
+fn lock_disks() -> Result<MutexGuard<'static, Disks>, StatError> {
+    DISKS.lock().map_err(|_| StatError::LockPoisoned)
+}
+
+fn lock_prev_cpu_times() -> Result<MutexGuard<'static, Vec<CpuTimes>>, StatError> {
+    PREV_CPU_TIMES.lock().map_err(|_| StatError::LockPoisoned)
+}
+
+fn lock_history() -> Result<MutexGuard<'static, VecDeque<StatSample>>, StatError> {
+    HISTORY.lock().map_err(|_| StatError::LockPoisoned)
+}
+
 lazy_static! {
     static ref SYSTEM: Mutex<System> = Mutex::new(System::new_all());
     static ref DISKS: Mutex<Disks> = Mutex::new(Disks::new_with_refreshed_list());
+    static ref PREV_CPU_TIMES: Mutex<Vec<CpuTimes>> = Mutex::new(Vec::new());
+    static ref HISTORY: Mutex<VecDeque<StatSample>> = Mutex::new(VecDeque::with_capacity(HISTORY_CAPACITY));
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-    const TB: u64 = GB * 1024;
+const KB: u64 = 1024;
+const MB: u64 = KB * 1024;
+const GB: u64 = MB * 1024;
+const TB: u64 = GB * 1024;
 
+fn format_bytes(bytes: u64) -> String {
     match bytes {
         b if b >= TB => format!("{:.2} TB", b as f64 / TB as f64),
         b if b >= GB => format!("{:.2} GB", b as f64 / GB as f64),
@@ -44,44 +274,324 @@ fn format_percentage(value: f32) -> String {
     format!("{:.2}%", value)
 }
 
-async fn get_server_stats() -> Json<ServerStats> {
-    let mut system = SYSTEM.lock().unwrap();
-    system.refresh_all();
+/// Like `format_bytes`, but `unit` can pin the scale instead of picking it
+/// automatically (the `unit` query hint on `/stats`).
+fn format_bytes_as(bytes: u64, unit: ByteUnit) -> String {
+    match unit {
+        ByteUnit::B => format!("{} B", bytes),
+        ByteUnit::Kb => format!("{:.2} KB", bytes as f64 / KB as f64),
+        ByteUnit::Mb => format!("{:.2} MB", bytes as f64 / MB as f64),
+        ByteUnit::Gb => format!("{:.2} GB", bytes as f64 / GB as f64),
+        ByteUnit::Tb => format!("{:.2} TB", bytes as f64 / TB as f64),
+        ByteUnit::Auto => format_bytes(bytes),
+    }
+}
+
+// Per-core user/system/idle/nice jiffies, read straight from /proc/stat since
+// sysinfo only exposes an already-aggregated usage percentage per core.
+fn read_cpu_times() -> Vec<CpuTimes> {
+    let contents = std::fs::read_to_string("/proc/stat").unwrap_or_default();
+    contents
+        .lines()
+        .filter(|line| line.starts_with("cpu") && !line.starts_with("cpu "))
+        .map(|line| {
+            let fields: Vec<u64> = line
+                .split_whitespace()
+                .skip(1)
+                .filter_map(|field| field.parse().ok())
+                .collect();
+            CpuTimes {
+                user: fields.first().copied().unwrap_or(0),
+                nice: fields.get(1).copied().unwrap_or(0),
+                system: fields.get(2).copied().unwrap_or(0),
+                idle: fields.get(3).copied().unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+fn cpu_time_breakdown(prev: &[CpuTimes], current: &[CpuTimes]) -> CpuTimeBreakdown {
+    let mut user = 0u64;
+    let mut system = 0u64;
+    let mut idle = 0u64;
+    let mut nice = 0u64;
+
+    for (p, c) in prev.iter().zip(current.iter()) {
+        user += c.user.saturating_sub(p.user);
+        system += c.system.saturating_sub(p.system);
+        idle += c.idle.saturating_sub(p.idle);
+        nice += c.nice.saturating_sub(p.nice);
+    }
+
+    let delta_total = user + system + idle + nice;
+    if delta_total == 0 {
+        return CpuTimeBreakdown {
+            user: format_percentage(0.0),
+            system: format_percentage(0.0),
+            idle: format_percentage(0.0),
+            nice: format_percentage(0.0),
+        };
+    }
+
+    CpuTimeBreakdown {
+        user: format_percentage(user as f32 / delta_total as f32 * 100.0),
+        system: format_percentage(system as f32 / delta_total as f32 * 100.0),
+        idle: format_percentage(idle as f32 / delta_total as f32 * 100.0),
+        nice: format_percentage(nice as f32 / delta_total as f32 * 100.0),
+    }
+}
+
+/// Refreshes `SYSTEM`/`DISKS` and appends a new `HISTORY` entry. This is the
+/// only place that calls `refresh_all`/`refresh_list` — request handlers read
+/// whatever this last left behind instead of forcing their own refresh.
+fn sample_once() {
+    let cpu_usage;
+    let ram_percentage;
+    {
+        // Nothing here can fail in a way a client could react to, so recover
+        // from a poisoned lock rather than taking the whole sampler down.
+        let mut system = SYSTEM.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        system.refresh_all();
+
+        cpu_usage = system.global_cpu_usage();
+        let total_memory = system.total_memory();
+        let used_memory = system.used_memory();
+        ram_percentage = if total_memory == 0 {
+            0.0
+        } else {
+            used_memory as f32 / total_memory as f32 * 100.0
+        };
+    }
+
+    let storage_percentage = {
+        let mut disks = DISKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        disks.refresh_list();
+        let total_disk_space = disks.iter().map(|disk| disk.total_space()).sum::<u64>();
+        let available_disk_space = disks.iter().map(|disk| disk.available_space()).sum::<u64>();
+        if total_disk_space == 0 {
+            0.0
+        } else {
+            100.0 - (available_disk_space as f32 / total_disk_space as f32) * 100.0
+        }
+    };
+
+    let mut history = HISTORY.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let prev_ema = history.back().map_or(cpu_usage, |sample| sample.cpu_usage_ema);
+    let cpu_usage_ema = EMA_ALPHA * cpu_usage + (1.0 - EMA_ALPHA) * prev_ema;
+
+    if history.len() == HISTORY_CAPACITY {
+        history.pop_front();
+    }
+    history.push_back(StatSample {
+        timestamp: Utc::now(),
+        cpu_usage,
+        cpu_usage_ema,
+        ram_percentage,
+        storage_percentage,
+    });
+}
+
+/// Load average is not meaningful on Windows, so callers there get `None`
+/// and `SystemInfo` omits the field entirely via `skip_serializing_if`.
+#[cfg(not(target_os = "windows"))]
+fn current_load_average() -> Option<LoadAverage> {
+    let load = System::load_average();
+    Some(LoadAverage {
+        one: load.one,
+        five: load.five,
+        fifteen: load.fifteen,
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn current_load_average() -> Option<LoadAverage> {
+    None
+}
+
+fn min_max_avg(values: impl Iterator<Item = f32>) -> MinMaxAvg {
+    let mut min = f32::INFINITY;
+    let mut max = f32::NEG_INFINITY;
+    let mut sum = 0.0;
+    let mut count = 0u32;
 
-    let cpu_usage = format_percentage(system.global_cpu_usage());
+    for value in values {
+        min = min.min(value);
+        max = max.max(value);
+        sum += value;
+        count += 1;
+    }
+
+    if count == 0 {
+        return MinMaxAvg { min: 0.0, max: 0.0, avg: 0.0 };
+    }
+
+    MinMaxAvg { min, max, avg: sum / count as f32 }
+}
+
+async fn get_server_stats(
+    RawQuery(raw_query): RawQuery,
+) -> Result<Json<ServerStatsResponse>, StatError> {
+    let params = StatsQuery::from_raw(raw_query.as_deref());
+    let raw_mode = params
+        .format
+        .as_deref()
+        .is_some_and(|format| format.eq_ignore_ascii_case("raw"));
+    let unit = ByteUnit::from_query(params.unit.as_deref());
+
+    let system = lock_system()?;
+    let cpu_usage = system.global_cpu_usage();
 
     let total_memory = system.total_memory();
     let used_memory = system.used_memory();
+    if total_memory == 0 {
+        return Err(StatError::Memory);
+    }
     let ram_percentage = (used_memory as f32 / total_memory as f32) * 100.0;
+    drop(system);
 
-    let ram = UsageInfo {
-        used: format_bytes(used_memory),
-        total: format_bytes(total_memory),
-        percentage: format_percentage(ram_percentage),
-    };
-
-    let mut disks = DISKS.lock().unwrap();
-    disks.refresh_list();
+    let disks = lock_disks()?;
     let total_disk_space = disks.iter().map(|disk| disk.total_space()).sum::<u64>();
     let available_disk_space = disks.iter().map(|disk| disk.available_space()).sum::<u64>();
+    if total_disk_space == 0 {
+        return Err(StatError::Disk);
+    }
+    let used_disk_space = total_disk_space - available_disk_space;
     let used_percentage = 100.0 - (available_disk_space as f32 / total_disk_space as f32) * 100.0;
+    drop(disks);
+
+    if raw_mode {
+        return Ok(Json(ServerStatsResponse::Raw(ServerStatsRaw {
+            cpu_usage: cpu_usage as f64,
+            ram: UsageInfoRaw {
+                used: used_memory,
+                total: total_memory,
+                percentage: ram_percentage as f64,
+            },
+            storage: UsageInfoRaw {
+                used: used_disk_space,
+                total: total_disk_space,
+                percentage: used_percentage as f64,
+            },
+        })));
+    }
+
+    Ok(Json(ServerStatsResponse::Pretty(ServerStats {
+        cpu_usage: format_percentage(cpu_usage),
+        ram: UsageInfo {
+            used: format_bytes_as(used_memory, unit),
+            total: format_bytes_as(total_memory, unit),
+            percentage: format_percentage(ram_percentage),
+        },
+        storage: UsageInfo {
+            used: format_bytes_as(used_disk_space, unit),
+            total: format_bytes_as(total_disk_space, unit),
+            percentage: format_percentage(used_percentage),
+        },
+    })))
+}
+
+async fn get_cpu_stats() -> Result<Json<CpuStats>, StatError> {
+    let system = lock_system()?;
+
+    if system.cpus().is_empty() {
+        return Err(StatError::Cpu);
+    }
+
+    let cores = system
+        .cpus()
+        .iter()
+        .map(|cpu| format_percentage(cpu.cpu_usage()))
+        .collect();
+    drop(system);
+
+    let current_times = read_cpu_times();
+    let mut prev_times = lock_prev_cpu_times()?;
+    let breakdown = cpu_time_breakdown(&prev_times, &current_times);
+    *prev_times = current_times;
+
+    Ok(Json(CpuStats { cores, breakdown }))
+}
 
-    let storage = UsageInfo {
-        used: format_bytes(total_disk_space - available_disk_space),
-        total: format_bytes(total_disk_space),
-        percentage: format_percentage(used_percentage),
+async fn get_history() -> Result<Json<HistoryResponse>, StatError> {
+    let history = lock_history()?;
+    let samples: Vec<StatSample> = history.iter().cloned().collect();
+    drop(history);
+
+    let stats = HistoryStats {
+        cpu: min_max_avg(samples.iter().map(|sample| sample.cpu_usage)),
+        ram: min_max_avg(samples.iter().map(|sample| sample.ram_percentage)),
+        storage: min_max_avg(samples.iter().map(|sample| sample.storage_percentage)),
     };
 
-    Json(ServerStats {
-        cpu_usage,
-        ram,
-        storage,
-    })
+    Ok(Json(HistoryResponse { samples, stats }))
+}
+
+async fn get_disk_stats() -> Result<Json<Vec<DiskInfo>>, StatError> {
+    let disks = lock_disks()?;
+
+    let result = disks
+        .iter()
+        .map(|disk| {
+            let total = disk.total_space();
+            let available = disk.available_space();
+            let used = total.saturating_sub(available);
+            let percentage = if total == 0 {
+                0.0
+            } else {
+                used as f32 / total as f32 * 100.0
+            };
+
+            DiskInfo {
+                mount_point: disk.mount_point().to_string_lossy().to_string(),
+                file_system: disk.file_system().to_string_lossy().to_string(),
+                device_name: disk.name().to_string_lossy().to_string(),
+                total: format_bytes(total),
+                available: format_bytes(available),
+                used: format_bytes(used),
+                percentage: format_percentage(percentage),
+                is_removable: disk.is_removable(),
+                is_read_only: disk.is_read_only(),
+            }
+        })
+        .collect();
+
+    Ok(Json(result))
+}
+
+async fn get_system_info() -> Result<Json<SystemInfo>, StatError> {
+    let system = lock_system()?;
+    let process_count = system.processes().len();
+    drop(system);
+
+    Ok(Json(SystemInfo {
+        load_average: current_load_average(),
+        uptime_seconds: System::uptime(),
+        boot_time: System::boot_time(),
+        hostname: System::host_name(),
+        os_name: System::name(),
+        os_version: System::os_version(),
+        kernel_version: System::kernel_version(),
+        process_count,
+    }))
 }
 
 #[tokio::main]
 async fn main() {
-    let app = Router::new().route("/stats", get(get_server_stats));
+    // Prime SYSTEM/DISKS/HISTORY before serving the first request.
+    sample_once();
+    tokio::spawn(async {
+        loop {
+            tokio::time::sleep(Duration::from_secs(SAMPLE_INTERVAL_SECS)).await;
+            sample_once();
+        }
+    });
+
+    let app = Router::new()
+        .route("/stats", get(get_server_stats))
+        .route("/stats/cpu", get(get_cpu_stats))
+        .route("/stats/history", get(get_history))
+        .route("/stats/system", get(get_system_info))
+        .route("/stats/disks", get(get_disk_stats));
     let addr = SocketAddr::from(([127, 0, 0, 1], 2989));
     println!("Listening on {}", addr);
     if let Err(e) = Server::bind(addr).serve(app.into_make_service()).await {